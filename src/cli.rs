@@ -1,3 +1,4 @@
+use std::fs;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
@@ -12,8 +13,13 @@ use crate::Error;
 /// Generate a PDF from a local HTML file using a headless chrome
 #[derive(Debug, Parser)]
 pub struct Options {
-    /// Input HTML file.
-    pub input: PathBuf,
+    /// Input HTML file(s).
+    /// Provide several files to merge them into a single PDF, in the given order.
+    /// Each input may override the paper size and/or orientation for that input only,
+    /// using `path,PAPER,landscape` or `path,PAPER,portrait`
+    /// (e.g. `cover.html,A4,landscape`).
+    #[clap(required = true)]
+    pub input: Vec<InputSpec>,
 
     /// Output file.
     /// By default, just change the input extension to PDF
@@ -28,11 +34,23 @@ pub struct Options {
     #[clap(long)]
     pub background: bool,
 
-    /// Time to wait in ms before printing.
+    /// Time to wait before printing.
     /// Examples: 150ms, 10s
+    /// When combined with `--wait-for-selector` or `--wait-for-network-idle`, this is
+    /// the upper-bound timeout for that strategy instead of a fixed sleep.
     #[clap(long, value_parser = parse_duration)]
     pub wait: Option<Duration>,
 
+    /// Wait until the given CSS selector appears in the page before printing, instead
+    /// of a fixed sleep. `--wait` bounds how long to wait.
+    #[clap(long)]
+    pub wait_for_selector: Option<String>,
+
+    /// Wait until the page's network has been idle for a short quiet period before
+    /// printing, instead of a fixed sleep. `--wait` bounds how long to wait.
+    #[clap(long)]
+    pub wait_for_network_idle: bool,
+
     /// HTML template for the print header.
     /// Should be valid HTML markup with following classes used to inject printing values into
     /// them:
@@ -45,11 +63,27 @@ pub struct Options {
     #[clap(long)]
     pub header: Option<String>,
 
+    /// Load the header template from a file instead of passing it inline.
+    /// Takes precedence over `--header` if both are given.
+    #[clap(long)]
+    pub header_file: Option<PathBuf>,
+
     /// HTML template for the print footer.
     /// Should use the same format as the headerTemplate.
     #[clap(long)]
     pub footer: Option<String>,
 
+    /// Load the footer template from a file instead of passing it inline.
+    /// Takes precedence over `--footer` if both are given.
+    #[clap(long)]
+    pub footer_file: Option<PathBuf>,
+
+    /// Use a ready-made footer template rendering "page X / Y", using Chrome's
+    /// `pageNumber` and `totalPages` classes. Ignored if `--footer`/`--footer-file` is
+    /// given.
+    #[clap(long)]
+    pub default_footer: bool,
+
     /// Paper size.
     /// Supported values: A4, Letter, A3, Tabloid, A2, A1, A0, A5, A6
     #[clap(long)]
@@ -76,12 +110,47 @@ pub struct Options {
     /// Not recommended, unless running on docker
     #[clap(long)]
     pub disable_sandbox: bool,
+
+    /// Prefer the page size declared by the page's own CSS `@page` rule
+    /// over the `--paper`/`--landscape` flags.
+    #[clap(long)]
+    pub prefer_css_page_size: bool,
+
+    /// Generate a tagged (accessible) PDF.
+    #[clap(long)]
+    pub tagged_pdf: bool,
+
+    /// Generate a document outline (bookmarks tree).
+    ///
+    /// Only preserved for a single input rendered without `--two-sided`: merging
+    /// multiple inputs or rendering two-sided always produces a new page tree, and
+    /// the outline is dropped rather than remapped onto it.
+    #[clap(long)]
+    pub outline: bool,
+
+    /// Don't fail when `--range` contains a page range outside the
+    /// document's page count, just ignore it.
+    #[clap(long)]
+    pub ignore_invalid_page_ranges: bool,
+
+    /// Two-sided (duplex) printing.
+    /// Odd (right-hand) pages get the larger margin on the left, even
+    /// (left-hand) pages get it on the right, so the document can be bound.
+    /// Use the `inner:.. outer:.. top:.. bottom:..` form of `--margin` to
+    /// define the inner/outer margins.
+    #[clap(long)]
+    pub two_sided: bool,
+
+    /// Extra margin added to the inner (binding) edge, e.g. to account for
+    /// staples or a thick binding. Only used with `--two-sided`.
+    #[clap(long)]
+    pub binding_offset: Option<f64>,
 }
 
 impl Options {
-    /// Get a reference to the cli options's input.
+    /// Get a reference to the cli options's input(s).
     #[must_use]
-    pub fn input(&self) -> &PathBuf {
+    pub fn inputs(&self) -> &[InputSpec] {
         &self.input
     }
 
@@ -109,18 +178,103 @@ impl Options {
         self.wait
     }
 
+    /// Get a reference to the cli options's wait-for-selector.
+    #[must_use]
+    pub fn wait_for_selector(&self) -> Option<&String> {
+        self.wait_for_selector.as_ref()
+    }
+
+    /// Get a reference to the cli options's wait-for-network-idle.
+    #[must_use]
+    pub fn wait_for_network_idle(&self) -> bool {
+        self.wait_for_network_idle
+    }
+
+    /// The readiness strategy to use before exporting to PDF, combining `--wait`,
+    /// `--wait-for-selector` and `--wait-for-network-idle`.
+    #[must_use]
+    pub fn wait_strategy(&self) -> WaitStrategy {
+        if let Some(selector) = self.wait_for_selector().cloned() {
+            WaitStrategy::Selector {
+                selector,
+                timeout: self.wait().unwrap_or(DEFAULT_WAIT_TIMEOUT),
+            }
+        } else if self.wait_for_network_idle() {
+            WaitStrategy::NetworkIdle {
+                timeout: self.wait().unwrap_or(DEFAULT_WAIT_TIMEOUT),
+            }
+        } else if let Some(wait) = self.wait() {
+            WaitStrategy::Sleep(wait)
+        } else {
+            WaitStrategy::None
+        }
+    }
+
     /// Get a reference to the cli options's header.
     #[must_use]
     pub fn header(&self) -> Option<&String> {
         self.header.as_ref()
     }
 
+    /// Get a reference to the cli options's header file.
+    #[must_use]
+    pub fn header_file(&self) -> Option<&PathBuf> {
+        self.header_file.as_ref()
+    }
+
     /// Get a reference to the cli options's footer.
     #[must_use]
     pub fn footer(&self) -> Option<&String> {
         self.footer.as_ref()
     }
 
+    /// Get a reference to the cli options's footer file.
+    #[must_use]
+    pub fn footer_file(&self) -> Option<&PathBuf> {
+        self.footer_file.as_ref()
+    }
+
+    /// Get a reference to the cli options's default footer flag.
+    #[must_use]
+    pub fn default_footer(&self) -> bool {
+        self.default_footer
+    }
+
+    /// Resolve the effective header template: `--header-file` if given, else
+    /// `--header`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `--header-file` points to an unreadable file.
+    pub fn header_template(&self) -> Result<Option<String>, Error> {
+        self.header_file
+            .as_ref()
+            .map(fs::read_to_string)
+            .transpose()
+            .map(|from_file| from_file.or_else(|| self.header().cloned()))
+            .map_err(Error::from)
+    }
+
+    /// Resolve the effective footer template: `--footer-file` if given, else
+    /// `--footer`, else the built-in `--default-footer` template.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `--footer-file` points to an unreadable file.
+    pub fn footer_template(&self) -> Result<Option<String>, Error> {
+        self.footer_file
+            .as_ref()
+            .map(fs::read_to_string)
+            .transpose()
+            .map(|from_file| {
+                from_file.or_else(|| self.footer().cloned()).or_else(|| {
+                    self.default_footer()
+                        .then(|| DEFAULT_FOOTER_TEMPLATE.to_string())
+                })
+            })
+            .map_err(Error::from)
+    }
+
     /// Get a reference to the cli options's paper.
     #[must_use]
     pub fn paper(&self) -> Option<&PaperSize> {
@@ -150,13 +304,58 @@ impl Options {
     pub fn disable_sandbox(&self) -> bool {
         self.disable_sandbox
     }
+
+    /// Get a reference to the cli options's prefer css page size.
+    #[must_use]
+    pub fn prefer_css_page_size(&self) -> bool {
+        self.prefer_css_page_size
+    }
+
+    /// Get a reference to the cli options's tagged pdf.
+    #[must_use]
+    pub fn tagged_pdf(&self) -> bool {
+        self.tagged_pdf
+    }
+
+    /// Get a reference to the cli options's outline.
+    #[must_use]
+    pub fn outline(&self) -> bool {
+        self.outline
+    }
+
+    /// Get a reference to the cli options's ignore invalid page ranges.
+    #[must_use]
+    pub fn ignore_invalid_page_ranges(&self) -> bool {
+        self.ignore_invalid_page_ranges
+    }
+
+    /// Get a reference to the cli options's two-sided mode.
+    #[must_use]
+    pub fn two_sided(&self) -> bool {
+        self.two_sided
+    }
+
+    /// Get a reference to the cli options's binding offset.
+    #[must_use]
+    pub fn binding_offset(&self) -> Option<f64> {
+        self.binding_offset
+    }
 }
 
 impl From<&Options> for PrintToPdfOptions {
     fn from(opt: &Options) -> Self {
+        // The header/footer templates can come from a file, so resolving them is
+        // fallible. Errors (e.g. a missing file) are ignored here so this conversion
+        // stays infallible for callers of the public API; `render_input` re-resolves
+        // them itself to surface the error and warn about margins that would clip
+        // the template.
+        let header_template = opt.header_template().unwrap_or_default();
+        let footer_template = opt.footer_template().unwrap_or_default();
+        let has_header_or_footer = header_template.is_some() || footer_template.is_some();
+
         PrintToPdfOptions {
             landscape: Some(opt.landscape()),
-            display_header_footer: Some(opt.header().is_some() || opt.footer().is_some()),
+            display_header_footer: Some(has_header_or_footer),
             print_background: Some(opt.background()),
             scale: opt.scale(),
             paper_width: opt.paper().map(PaperSize::paper_width),
@@ -166,13 +365,13 @@ impl From<&Options> for PrintToPdfOptions {
             margin_left: opt.margin().map(Margin::margin_left),
             margin_right: opt.margin().map(Margin::margin_right),
             page_ranges: opt.range().cloned(),
-            ignore_invalid_page_ranges: None,
-            header_template: opt.header().cloned(),
-            footer_template: opt.footer().cloned(),
-            prefer_css_page_size: None,
+            ignore_invalid_page_ranges: Some(opt.ignore_invalid_page_ranges()),
+            header_template,
+            footer_template,
+            prefer_css_page_size: Some(opt.prefer_css_page_size()),
             transfer_mode: None,
-            generate_document_outline: None,
-            generate_tagged_pdf: None,
+            generate_document_outline: Some(opt.outline()),
+            generate_tagged_pdf: Some(opt.tagged_pdf()),
         }
     }
 }
@@ -187,6 +386,100 @@ impl From<&Options> for LaunchOptions<'_> {
     }
 }
 
+/// Built-in footer template used by `--default-footer`, rendering "page X / Y" using
+/// the `pageNumber` and `totalPages` classes Chrome substitutes.
+const DEFAULT_FOOTER_TEMPLATE: &str = r#"<div style="font-size: 10px; width: 100%; text-align: center;"><span class="pageNumber"></span> / <span class="totalPages"></span></div>"#;
+
+/// The upper-bound timeout used by [`WaitStrategy::Selector`] and
+/// [`WaitStrategy::NetworkIdle`] when `--wait` isn't given.
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How to decide the page is ready to export to PDF.
+#[derive(Debug, Clone)]
+pub enum WaitStrategy {
+    /// Export as soon as the page has finished navigating.
+    None,
+
+    /// Sleep a fixed duration before exporting.
+    Sleep(Duration),
+
+    /// Poll until `selector` appears in the DOM, or `timeout` elapses.
+    Selector {
+        /// The CSS selector to poll for.
+        selector: String,
+        /// How long to poll before giving up and exporting anyway.
+        timeout: Duration,
+    },
+
+    /// Wait until the page's network has been idle for a quiet period, or `timeout`
+    /// elapses.
+    NetworkIdle {
+        /// How long to wait before giving up and exporting anyway.
+        timeout: Duration,
+    },
+}
+
+/// A single HTML input, with optional per-input paper size/orientation overrides.
+///
+/// When several inputs are given, each one can carry its own overrides so e.g. a
+/// landscape cover page can be followed by portrait A4 body pages in the same document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputSpec {
+    /// The HTML file path.
+    path: PathBuf,
+
+    /// Paper size override for this input only.
+    paper: Option<PaperSize>,
+
+    /// Landscape override for this input only.
+    landscape: Option<bool>,
+}
+
+impl InputSpec {
+    /// Get a reference to the input's path.
+    #[must_use]
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Get a reference to the input's paper size override.
+    #[must_use]
+    pub fn paper(&self) -> Option<&PaperSize> {
+        self.paper.as_ref()
+    }
+
+    /// Get a reference to the input's landscape override.
+    #[must_use]
+    pub fn landscape(&self) -> Option<bool> {
+        self.landscape
+    }
+}
+
+impl FromStr for InputSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let path = PathBuf::from(parts.next().unwrap_or_default());
+
+        let mut paper = None;
+        let mut landscape = None;
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "landscape" => landscape = Some(true),
+                "portrait" => landscape = Some(false),
+                _ => paper = Some(part.parse::<PaperSize>()?),
+            }
+        }
+
+        Ok(InputSpec {
+            path,
+            paper,
+            landscape,
+        })
+    }
+}
+
 /// Paper size
 #[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
 pub enum PaperSize {
@@ -272,6 +565,8 @@ impl FromStr for PaperSize {
             "tabloid" => Ok(Self::Tabloid),
             _ => Err(Error::InvalidPaperSize {
                 size: s.to_string(),
+                src: s.to_string(),
+                span: (0, s.len()).into(),
             }),
         }
     }
@@ -288,6 +583,20 @@ pub enum Margin {
 
     /// Custom margin for every side
     TopRightBottomLeft(f64, f64, f64, f64),
+
+    /// Inner/outer margin pair, for two-sided (duplex) printing.
+    /// The inner margin sits on the binding edge (left on odd pages, right on
+    /// even pages), the outer margin sits on the opposite edge.
+    InnerOuter {
+        /// Margin on the binding edge
+        inner: f64,
+        /// Margin on the edge opposite the binding
+        outer: f64,
+        /// Margin top
+        top: f64,
+        /// Margin bottom
+        bottom: f64,
+    },
 }
 
 impl Margin {
@@ -298,6 +607,7 @@ impl Margin {
             Margin::All(f)
             | Margin::VerticalHorizontal(f, _)
             | Margin::TopRightBottomLeft(f, _, _, _) => *f,
+            Margin::InnerOuter { top, .. } => *top,
         }
     }
     /// Margin right
@@ -307,6 +617,7 @@ impl Margin {
             Margin::All(f)
             | Margin::VerticalHorizontal(_, f)
             | Margin::TopRightBottomLeft(_, f, _, _) => *f,
+            Margin::InnerOuter { outer, .. } => *outer,
         }
     }
     /// Margin bottom
@@ -316,6 +627,7 @@ impl Margin {
             Margin::All(f)
             | Margin::VerticalHorizontal(f, _)
             | Margin::TopRightBottomLeft(_, _, f, _) => *f,
+            Margin::InnerOuter { bottom, .. } => *bottom,
         }
     }
     /// Margin left
@@ -325,6 +637,27 @@ impl Margin {
             Margin::All(f)
             | Margin::VerticalHorizontal(_, f)
             | Margin::TopRightBottomLeft(_, _, _, f) => *f,
+            Margin::InnerOuter { inner, .. } => *inner,
+        }
+    }
+
+    /// The margin on the binding edge, used for two-sided printing.
+    /// Falls back to [`Margin::margin_left`] for the other variants.
+    #[must_use]
+    pub fn inner(&self) -> f64 {
+        match self {
+            Margin::InnerOuter { inner, .. } => *inner,
+            _ => self.margin_left(),
+        }
+    }
+
+    /// The margin on the edge opposite the binding, used for two-sided printing.
+    /// Falls back to [`Margin::margin_right`] for the other variants.
+    #[must_use]
+    pub fn outer(&self) -> f64 {
+        match self {
+            Margin::InnerOuter { outer, .. } => *outer,
+            _ => self.margin_right(),
         }
     }
 }
@@ -333,31 +666,111 @@ impl FromStr for Margin {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let values: Vec<&str> = s.split(' ').filter(|s| !s.is_empty()).collect();
+        if s.contains(':') {
+            return parse_inner_outer(s);
+        }
+
+        let values = tokenize(s);
         match values.len() {
             1 => {
-                let value = s.parse::<f64>()?;
-                Ok(Margin::All(value))
+                let (offset, value) = values[0];
+                Ok(Margin::All(parse_margin_value(s, offset, value)?))
             }
             2 => {
-                let v = values[0].parse::<f64>()?;
-                let h = values[1].parse::<f64>()?;
+                let v = parse_margin_value(s, values[0].0, values[0].1)?;
+                let h = parse_margin_value(s, values[1].0, values[1].1)?;
                 Ok(Margin::VerticalHorizontal(v, h))
             }
             4 => {
-                let top = values[0].parse::<f64>()?;
-                let right = values[1].parse::<f64>()?;
-                let bottom = values[2].parse::<f64>()?;
-                let left = values[2].parse::<f64>()?;
+                let top = parse_margin_value(s, values[0].0, values[0].1)?;
+                let right = parse_margin_value(s, values[1].0, values[1].1)?;
+                let bottom = parse_margin_value(s, values[2].0, values[2].1)?;
+                let left = parse_margin_value(s, values[3].0, values[3].1)?;
                 Ok(Margin::TopRightBottomLeft(top, right, bottom, left))
             }
             _ => Err(Error::InvalidMarginDefinition {
                 margin: s.to_string(),
+                src: s.to_string(),
+                span: (0, s.len()).into(),
             }),
         }
     }
 }
 
+/// Split a margin definition on spaces, keeping the byte offset of each token within
+/// `s` so parse errors can point at the exact offending value.
+fn tokenize(s: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    for part in s.split(' ') {
+        if !part.is_empty() {
+            tokens.push((offset, part));
+        }
+        offset += part.len() + 1;
+    }
+    tokens
+}
+
+/// Parse a single margin sub-value, turning a failure into an [`Error::InvalidMarginValue`]
+/// pointing at the exact `(offset, len)` span of `value` within `src`.
+fn parse_margin_value(src: &str, offset: usize, value: &str) -> Result<f64, Error> {
+    value
+        .parse::<f64>()
+        .map_err(|source| Error::InvalidMarginValue {
+            source,
+            src: src.to_string(),
+            span: (offset, value.len()).into(),
+        })
+}
+
+/// Parse the `inner:.. outer:.. top:.. bottom:..` form of [`Margin`], used for
+/// two-sided printing. All four keys are required.
+fn parse_inner_outer(s: &str) -> Result<Margin, Error> {
+    let mut inner = None;
+    let mut outer = None;
+    let mut top = None;
+    let mut bottom = None;
+
+    for (offset, token) in tokenize(s) {
+        let (key, value) = token
+            .split_once(':')
+            .ok_or_else(|| Error::InvalidMarginDefinition {
+                margin: s.to_string(),
+                src: s.to_string(),
+                span: (offset, token.len()).into(),
+            })?;
+        let value_offset = offset + key.len() + 1;
+        let value = parse_margin_value(s, value_offset, value)?;
+        match key.to_ascii_lowercase().as_str() {
+            "inner" => inner = Some(value),
+            "outer" => outer = Some(value),
+            "top" => top = Some(value),
+            "bottom" => bottom = Some(value),
+            _ => {
+                return Err(Error::InvalidMarginDefinition {
+                    margin: s.to_string(),
+                    src: s.to_string(),
+                    span: (offset, token.len()).into(),
+                })
+            }
+        }
+    }
+
+    match (inner, outer, top, bottom) {
+        (Some(inner), Some(outer), Some(top), Some(bottom)) => Ok(Margin::InnerOuter {
+            inner,
+            outer,
+            top,
+            bottom,
+        }),
+        _ => Err(Error::InvalidMarginDefinition {
+            margin: s.to_string(),
+            src: s.to_string(),
+            span: (0, s.len()).into(),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert2::{check, let_assert};
@@ -406,7 +819,11 @@ mod tests {
     fn should_parse_valid_margin_trbl() {
         let value = "0.2   0.3 0.4  0.5";
         let result = value.parse::<Margin>();
-        let_assert!(Ok(Margin::TopRightBottomLeft(_, _, _, _)) = result);
+        let_assert!(Ok(Margin::TopRightBottomLeft(top, right, bottom, left)) = result);
+        check!(top == 0.2);
+        check!(right == 0.3);
+        check!(bottom == 0.4);
+        check!(left == 0.5);
     }
 
     #[test]
@@ -420,6 +837,45 @@ mod tests {
     fn should_reject_invalid_margin_value() {
         let value = "plop";
         let result = value.parse::<Margin>();
-        let_assert!(Err(Error::InvalidMarginValue(_)) = result);
+        let_assert!(Err(Error::InvalidMarginValue { .. }) = result);
+    }
+
+    #[test]
+    fn should_parse_valid_margin_inner_outer() {
+        let value = "inner:0.6 outer:0.4 top:0.4 bottom:0.4";
+        let result = value.parse::<Margin>();
+        let_assert!(Ok(Margin::InnerOuter { .. }) = result);
+    }
+
+    #[test]
+    fn should_reject_incomplete_margin_inner_outer() {
+        let value = "inner:0.6 outer:0.4";
+        let result = value.parse::<Margin>();
+        let_assert!(Err(Error::InvalidMarginDefinition { .. }) = result);
+    }
+
+    #[test]
+    fn should_parse_input_spec_without_override() {
+        let value = "index.html";
+        let result = value.parse::<InputSpec>().unwrap();
+        check!(result.path() == &PathBuf::from("index.html"));
+        check!(result.paper().is_none());
+        check!(result.landscape().is_none());
+    }
+
+    #[test]
+    fn should_parse_input_spec_with_paper_and_orientation() {
+        let value = "cover.html,A4,landscape";
+        let result = value.parse::<InputSpec>().unwrap();
+        check!(result.path() == &PathBuf::from("cover.html"));
+        check!(result.paper() == Some(&PaperSize::A4));
+        check!(result.landscape() == Some(true));
+    }
+
+    #[test]
+    fn should_reject_input_spec_with_invalid_paper() {
+        let value = "cover.html,plop";
+        let result = value.parse::<InputSpec>();
+        let_assert!(Err(Error::InvalidPaperSize { .. }) = result);
     }
 }
@@ -11,71 +11,229 @@ use std::io::ErrorKind;
 use std::num::ParseFloatError;
 use std::path::Path;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{fs, io};
 
 use anyhow::Result;
 use headless_chrome::types::PrintToPdfOptions;
-use headless_chrome::{Browser, LaunchOptions};
-use tracing::{debug, info};
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use miette::SourceSpan;
+use tracing::{debug, info, warn};
 
 mod cli;
+mod merge;
 
 pub use cli::*;
 
+/// How long the network must stay free of in-flight requests before
+/// [`WaitStrategy::NetworkIdle`] considers the page ready.
+const NETWORK_IDLE_QUIET_PERIOD: Duration = Duration::from_millis(500);
+
+/// Polling interval while waiting for [`WaitStrategy::NetworkIdle`].
+const NETWORK_IDLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 /// The html2pdf Error
-#[derive(Debug, derive_more::Error, derive_more::Display, derive_more::From)]
+#[derive(
+    Debug, derive_more::Error, derive_more::Display, derive_more::From, miette::Diagnostic,
+)]
 pub enum Error {
     /// Invalid paper size
     #[display(
         "Invalid paper size {size}, expected a value in A4, Letter, A3, Tabloid, A2, A1, A0, A5, A6"
     )]
+    #[diagnostic(
+        code(html2pdf::invalid_paper_size),
+        help("accepted values: A4, Letter, Legal, A3, Tabloid, A2, A1, A0, A5, A6")
+    )]
     #[from(ignore)]
     InvalidPaperSize {
         /// The invalid size
         size: String,
+
+        /// The full `--paper` value that was parsed
+        #[source_code]
+        src: String,
+
+        /// The span of the offending value
+        #[label("not a valid paper size")]
+        span: SourceSpan,
     },
 
     /// Invalid margin definition
     #[display("Invalid margin definition, expected 1, 2, or 4 value, got {margin}")]
+    #[diagnostic(
+        code(html2pdf::invalid_margin_definition),
+        help("provide 1 value (all sides), 2 values (vertical horizontal), or 4 values (top right bottom left)")
+    )]
     #[from(ignore)]
     InvalidMarginDefinition {
         /// the invalid margin
         margin: String,
+
+        /// The full `--margin` value that was parsed
+        #[source_code]
+        src: String,
+
+        /// The span covering the whole value for a value-count mismatch or an incomplete
+        /// `inner:.. outer:.. top:.. bottom:..` form, or the single malformed token of that form
+        #[label("expected 1, 2 or 4 space-separated values here")]
+        span: SourceSpan,
     },
 
     /// Invalid margin value
-    #[display("Invalid margin value: {_0}")]
-    InvalidMarginValue(ParseFloatError),
+    #[display("Invalid margin value: {source}")]
+    #[diagnostic(
+        code(html2pdf::invalid_margin_value),
+        help("expected a floating point number, e.g. `0.4`")
+    )]
+    #[from(ignore)]
+    InvalidMarginValue {
+        /// the underlying parse error
+        source: ParseFloatError,
+
+        /// The full `--margin` value that was parsed
+        #[source_code]
+        src: String,
+
+        /// The span of the specific sub-value that failed to parse
+        #[label("not a number")]
+        span: SourceSpan,
+    },
 
     /// Headless chrome issue
     #[display("Oops, an error occurs with headless chrome: {_0}")]
     HeadlessChromeError(anyhow::Error),
 
+    /// PDF merge issue
+    #[display("Oops, an error occurs while merging the PDF documents: {_0}")]
+    PdfMergeError(lopdf::Error),
+
     /// I/O issue
     IoError(io::Error),
 }
 
 /// Run HTML to PDF with `headless_chrome`
 ///
+/// When several inputs are given, each one is rendered separately (honoring its own
+/// paper size/orientation overrides), then the resulting documents are merged into a
+/// single PDF, preserving each input's own page dimensions. The common case of a
+/// single, non-two-sided input skips the merge step entirely, so Chrome's own PDF
+/// bytes are written out unmodified rather than round-tripped through `lopdf`.
+///
 /// # Errors
 ///
-/// Could fail if there is I/O or Chrome headless issue
+/// Could fail if there is I/O, a Chrome headless issue, or a PDF merge issue
 pub fn run(opt: &Options) -> Result<(), Error> {
-    let input = dunce::canonicalize(opt.input())?;
     let output = if let Some(path) = opt.output() {
         path.clone()
     } else {
-        let mut path = opt.input().clone();
+        let mut path = opt.inputs()[0].path().clone();
         path.set_extension("pdf");
         path
     };
 
-    html_to_pdf(input, output, opt.into(), opt.into(), opt.wait())?;
+    if opt.outline() && (opt.inputs().len() > 1 || opt.two_sided()) {
+        warn!(
+            "--outline is only preserved for a single input rendered without \
+             --two-sided; it will be dropped while merging the rendered pages"
+        );
+    }
+
+    let mut documents = Vec::with_capacity(opt.inputs().len());
+    for input in opt.inputs() {
+        documents.push(render_input(opt, input)?);
+    }
+
+    let pdf = if documents.len() == 1 {
+        documents.remove(0)
+    } else {
+        merge::merge_pdfs(documents)?
+    };
+
+    info!(?output, "Output file");
+    fs::write(output, pdf)?;
 
     Ok(())
 }
 
+/// Render a single input, honoring its per-input overrides and, when
+/// [`Options::two_sided`] is set, rendering it twice (odd then even pages, with
+/// swapped inner/outer margins) and interleaving the result back into order.
+fn render_input(opt: &Options, input: &InputSpec) -> Result<Vec<u8>, Error> {
+    let path = dunce::canonicalize(input.path())?;
+
+    let mut pdf_options: PrintToPdfOptions = opt.into();
+    if let Some(landscape) = input.landscape() {
+        pdf_options.landscape = Some(landscape);
+    }
+    if let Some(paper) = input.paper() {
+        pdf_options.paper_width = Some(paper.paper_width());
+        pdf_options.paper_height = Some(paper.paper_height());
+    }
+
+    let header_template = opt.header_template()?;
+    let footer_template = opt.footer_template()?;
+    let has_header_or_footer = header_template.is_some() || footer_template.is_some();
+    if has_header_or_footer {
+        warn_if_margins_clip_header_footer(opt.margin());
+    }
+    pdf_options.display_header_footer = Some(has_header_or_footer);
+    pdf_options.header_template = header_template;
+    pdf_options.footer_template = footer_template;
+
+    if opt.two_sided() {
+        render_two_sided(opt, path, pdf_options)
+    } else {
+        render_to_pdf(path, pdf_options, opt.into(), opt.wait_strategy())
+    }
+}
+
+/// Render a document twice with swapped inner/outer margins (once laid out entirely
+/// with odd-page margins, once entirely with even-page margins), then interleave the
+/// two results back into their original order, keeping only the odd pages from the
+/// first and only the even pages from the second.
+///
+/// Both renders cover every page (rather than a pre-computed odd/even page range),
+/// because asymmetric margins can reflow content and change the page count: deciding
+/// the ranges up front from a throwaway symmetric render would desync the two
+/// passes. Instead [`merge::interleave_two_sided`] derives which pages are odd/even
+/// from each pass's own, actually-rendered page count.
+fn render_two_sided(
+    opt: &Options,
+    path: impl AsRef<Path> + Debug + Clone,
+    pdf_options: PrintToPdfOptions,
+) -> Result<Vec<u8>, Error> {
+    let binding_offset = opt.binding_offset().unwrap_or(0.0);
+    let inner = opt.margin().map_or(1.0, Margin::inner) + binding_offset;
+    let outer = opt.margin().map_or(1.0, Margin::outer);
+
+    let mut odd_options = pdf_options.clone();
+    odd_options.margin_left = Some(inner);
+    odd_options.margin_right = Some(outer);
+
+    let mut even_options = pdf_options;
+    even_options.margin_left = Some(outer);
+    even_options.margin_right = Some(inner);
+
+    let odd = render_to_pdf(path.clone(), odd_options, opt.into(), opt.wait_strategy())?;
+    let even = render_to_pdf(path, even_options, opt.into(), opt.wait_strategy())?;
+
+    merge::interleave_two_sided(odd, even)
+}
+
+/// Chrome hides the header/footer template when the corresponding margin is zero,
+/// so warn the user rather than silently clipping their template.
+fn warn_if_margins_clip_header_footer(margin: Option<&Margin>) {
+    let top = margin.map_or(1.0, Margin::margin_top);
+    let bottom = margin.map_or(1.0, Margin::margin_bottom);
+    if top <= 0.0 || bottom <= 0.0 {
+        warn!(
+            top,
+            bottom, "A header/footer template is set, but the top or bottom margin is zero: Chrome will clip it"
+        );
+    }
+}
+
 /// Run HTML to PDF with `headless_chrome`
 ///
 /// # Panics
@@ -89,11 +247,28 @@ pub fn html_to_pdf<I, O>(
     output: O,
     pdf_options: PrintToPdfOptions,
     launch_options: LaunchOptions,
-    wait: Option<Duration>,
+    wait: WaitStrategy,
 ) -> Result<(), Error>
 where
     I: AsRef<Path> + Debug,
     O: AsRef<Path> + Debug,
+{
+    let local_pdf = render_to_pdf(input, pdf_options, launch_options, wait)?;
+
+    info!(?output, "Output file");
+    fs::write(output.as_ref(), local_pdf)?;
+
+    Ok(())
+}
+
+fn render_to_pdf<I>(
+    input: I,
+    pdf_options: PrintToPdfOptions,
+    launch_options: LaunchOptions,
+    wait: WaitStrategy,
+) -> Result<Vec<u8>, Error>
+where
+    I: AsRef<Path> + Debug,
 {
     let os = input
         .as_ref()
@@ -103,31 +278,95 @@ where
     let input = format!("file://{os}");
     info!(%input, "Input file");
 
-    let local_pdf = print_to_pdf(&input, pdf_options, launch_options, wait)?;
+    let pdf = print_to_pdf(&input, pdf_options, launch_options, wait)?;
 
-    info!(?output, "Output file");
-    fs::write(output.as_ref(), local_pdf)?;
-
-    Ok(())
+    Ok(pdf)
 }
 
 fn print_to_pdf(
     file_path: &str,
     pdf_options: PrintToPdfOptions,
     launch_options: LaunchOptions,
-    wait: Option<Duration>,
+    wait: WaitStrategy,
 ) -> Result<Vec<u8>> {
     let browser = Browser::new(launch_options)?;
     let tab = browser.new_tab()?;
     let tab = tab.navigate_to(file_path)?.wait_until_navigated()?;
 
-    if let Some(wait) = wait {
-        info!(?wait, "Waiting before export to PDF");
-        sleep(wait);
-    }
+    wait_until_ready(tab, wait)?;
 
     debug!(?pdf_options, "Using PDF options");
     let bytes = tab.print_to_pdf(Some(pdf_options))?;
 
     Ok(bytes)
 }
+
+/// Block until the page is considered ready to export, according to `wait`.
+fn wait_until_ready(tab: &Tab, wait: WaitStrategy) -> Result<()> {
+    match wait {
+        WaitStrategy::None => {}
+        WaitStrategy::Sleep(duration) => {
+            info!(?duration, "Waiting before export to PDF");
+            sleep(duration);
+        }
+        WaitStrategy::Selector { selector, timeout } => {
+            info!(%selector, ?timeout, "Waiting for selector before export to PDF");
+            if let Err(error) = tab.wait_for_element_with_custom_timeout(&selector, timeout) {
+                warn!(%selector, %error, "Selector never appeared, exporting anyway");
+            }
+        }
+        WaitStrategy::NetworkIdle { timeout } => {
+            info!(?timeout, "Waiting for network idle before export to PDF");
+            wait_for_network_idle(tab, timeout)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Wait until no network request has been in flight for [`NETWORK_IDLE_QUIET_PERIOD`],
+/// or `timeout` elapses, whichever comes first.
+fn wait_for_network_idle(tab: &Tab, timeout: Duration) -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use headless_chrome::browser::tab::Event;
+    use headless_chrome::protocol::cdp::Network;
+
+    let in_flight = Arc::new(AtomicUsize::new(0));
+
+    tab.call_method(Network::Enable {
+        max_total_buffer_size: None,
+        max_resource_buffer_size: None,
+        max_post_data_size: None,
+    })?;
+
+    let listener_in_flight = in_flight.clone();
+    tab.add_event_listener(Arc::new(move |event: &Event| match event {
+        Event::NetworkRequestWillBeSent(_) => {
+            listener_in_flight.fetch_add(1, Ordering::SeqCst);
+        }
+        Event::NetworkLoadingFinished(_) | Event::NetworkLoadingFailed(_) => {
+            // Saturating: a request already in flight when `Network.Enable` was
+            // issued can finish without a matching `NetworkRequestWillBeSent`, and a
+            // plain `fetch_sub` would underflow and never read zero again.
+            let _ = listener_in_flight.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                Some(count.saturating_sub(1))
+            });
+        }
+        _ => {}
+    }))?;
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if in_flight.load(Ordering::SeqCst) == 0 {
+            sleep(NETWORK_IDLE_QUIET_PERIOD);
+            if in_flight.load(Ordering::SeqCst) == 0 {
+                break;
+            }
+        }
+        sleep(NETWORK_IDLE_POLL_INTERVAL);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use lopdf::{Document, Object, ObjectId};
+
+use crate::Error;
+
+/// Merge several rendered PDF documents into a single one, concatenating their page
+/// trees in order.
+///
+/// Each source document keeps its own page objects (and therefore its own `MediaBox`),
+/// so mixed page dimensions (e.g. a landscape cover page followed by portrait body
+/// pages) are preserved rather than forced to a uniform size.
+pub(crate) fn merge_pdfs(documents: Vec<Vec<u8>>) -> Result<Vec<u8>, Error> {
+    let documents = documents
+        .into_iter()
+        .map(|bytes| Document::load_mem(&bytes))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    merge_with_order(documents, |groups| groups.into_iter().flatten().collect())
+}
+
+/// Interleave the pages of two fully-rendered PDF documents back into a single
+/// sequence: odd page from `odd`, even page from `even`, odd page from `odd`, etc.
+///
+/// Used for two-sided printing, where `odd` and `even` are each a complete render of
+/// the whole document (with swapped inner/outer margins), so that an asymmetric
+/// margin can reflow content without desyncing the two passes. Which pages count as
+/// odd/even is derived from each document's own, actually-rendered page list, rather
+/// than a page count computed ahead of time.
+pub(crate) fn interleave_two_sided(odd: Vec<u8>, even: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let odd = Document::load_mem(&odd)?;
+    let even = Document::load_mem(&even)?;
+
+    merge_with_order(vec![odd, even], |mut groups| {
+        let even_pages = groups.pop().unwrap_or_default();
+        let odd_pages = groups.pop().unwrap_or_default();
+
+        // Keep only the odd-numbered pages (1, 3, 5, …) of the odd-margin render,
+        // and only the even-numbered pages (2, 4, 6, …) of the even-margin render.
+        let mut odd_pages = odd_pages.into_iter().step_by(2);
+        let mut even_pages = even_pages.into_iter().skip(1).step_by(2);
+        let mut pages = Vec::new();
+        loop {
+            match (odd_pages.next(), even_pages.next()) {
+                (Some(odd), Some(even)) => {
+                    pages.push(odd);
+                    pages.push(even);
+                }
+                (Some(odd), None) => pages.push(odd),
+                (None, Some(even)) => pages.push(even),
+                (None, None) => break,
+            }
+        }
+        pages
+    })
+}
+
+/// Merge the page trees of `documents`, ordering the final page list with `order`,
+/// which receives each document's own pages (in that document's order) and decides
+/// how to flatten them into the merged `Kids` array.
+fn merge_with_order(
+    mut documents: Vec<Document>,
+    order: impl FnOnce(Vec<Vec<ObjectId>>) -> Vec<ObjectId>,
+) -> Result<Vec<u8>, Error> {
+    if documents.len() == 1 {
+        let mut document = documents.remove(0);
+        return save(&mut document);
+    }
+
+    let mut max_id = 1;
+    let mut page_groups = Vec::with_capacity(documents.len());
+    let mut documents_pages = BTreeMap::new();
+    let mut documents_objects = BTreeMap::new();
+
+    for mut document in documents {
+        document.renumber_objects_with(max_id);
+        max_id = document.max_id + 1;
+
+        let page_ids: Vec<ObjectId> = document.get_pages().into_values().collect();
+        documents_pages.extend(page_ids.iter().map(|&object_id| {
+            (
+                object_id,
+                document.get_object(object_id).unwrap().to_owned(),
+            )
+        }));
+        page_groups.push(page_ids);
+        documents_objects.extend(document.objects);
+    }
+
+    // Decide the final page order/selection up front so that pages left out of it
+    // (e.g. the other half of a two-sided render) and anything only they reach
+    // (content streams, images, fonts, ...) can be excluded below, instead of being
+    // serialized as dead weight alongside the merged document.
+    let page_ids = order(page_groups);
+    let selected_pages: BTreeSet<ObjectId> = page_ids.iter().copied().collect();
+    let reachable = reachable_objects(page_ids.iter().copied(), &documents_objects);
+
+    let mut catalog_object: Option<(ObjectId, Object)> = None;
+    let mut pages_object: Option<(ObjectId, Object)> = None;
+
+    // Collect every `Catalog` and `Pages` object, merging the `Pages` dictionaries
+    // found along the way. Individual `Page` and `Outline(s)` objects are dropped here:
+    // pages are re-inserted below with their `Parent` pointing at the merged page tree,
+    // and outlines (bookmarks) don't carry over across merged documents. Everything
+    // else is only kept if it's reachable from a selected page.
+    let mut document = Document::with_version("1.5");
+    for (object_id, object) in &documents_objects {
+        match object.type_name().unwrap_or_default() {
+            "Catalog" => {
+                catalog_object = Some((*object_id, object.clone()));
+            }
+            "Pages" => {
+                if let Ok(dictionary) = object.as_dict() {
+                    let mut dictionary = dictionary.clone();
+                    if let Some((_, existing)) = &pages_object {
+                        if let Ok(existing) = existing.as_dict() {
+                            dictionary.extend(existing.clone());
+                        }
+                    }
+                    pages_object = Some((*object_id, Object::Dictionary(dictionary)));
+                }
+            }
+            "Page" | "Outlines" | "Outline" => {}
+            _ if reachable.contains(object_id) => {
+                document.objects.insert(*object_id, object.clone());
+            }
+            _ => {}
+        }
+    }
+
+    let (pages_object_id, pages_object) =
+        pages_object.ok_or(Error::PdfMergeError(lopdf::Error::ObjectNotFound))?;
+    let (catalog_object_id, catalog_object) =
+        catalog_object.ok_or(Error::PdfMergeError(lopdf::Error::ObjectNotFound))?;
+
+    for (object_id, object) in documents_pages {
+        if !selected_pages.contains(&object_id) {
+            continue;
+        }
+        if let Ok(dictionary) = object.as_dict() {
+            let mut dictionary = dictionary.clone();
+            dictionary.set("Parent", pages_object_id);
+            document
+                .objects
+                .insert(object_id, Object::Dictionary(dictionary));
+        }
+    }
+
+    if let Ok(dictionary) = pages_object.as_dict() {
+        let mut dictionary = dictionary.clone();
+        dictionary.set("Count", page_ids.len() as i64);
+        dictionary.set(
+            "Kids",
+            page_ids
+                .into_iter()
+                .map(Object::Reference)
+                .collect::<Vec<_>>(),
+        );
+        document
+            .objects
+            .insert(pages_object_id, Object::Dictionary(dictionary));
+    }
+
+    if let Ok(dictionary) = catalog_object.as_dict() {
+        let mut dictionary = dictionary.clone();
+        dictionary.set("Pages", pages_object_id);
+        dictionary.remove(b"Outlines");
+        document
+            .objects
+            .insert(catalog_object_id, Object::Dictionary(dictionary));
+    }
+
+    document.trailer.set("Root", catalog_object_id);
+    document.max_id = document.objects.len() as u32;
+    document.renumber_objects();
+    document.adjust_zero_pages();
+
+    save(&mut document)
+}
+
+/// Walk every `Object::Reference` transitively reachable from `roots` within `pool`
+/// (e.g. a page's content stream, fonts, and images).
+fn reachable_objects(
+    roots: impl IntoIterator<Item = ObjectId>,
+    pool: &BTreeMap<ObjectId, Object>,
+) -> BTreeSet<ObjectId> {
+    let mut seen = BTreeSet::new();
+    let mut pending: Vec<ObjectId> = roots.into_iter().collect();
+
+    while let Some(object_id) = pending.pop() {
+        if !seen.insert(object_id) {
+            continue;
+        }
+        if let Some(object) = pool.get(&object_id) {
+            match object.as_dict() {
+                // A page's `Parent` leads to its `Pages` node, whose `Kids` would pull
+                // in every page of that document, selected or not.
+                Ok(dictionary) if object.type_name().unwrap_or_default() == "Pages" => {
+                    for (key, item) in dictionary.iter() {
+                        if key.as_slice() != b"Kids".as_slice() {
+                            collect_references(item, &mut pending);
+                        }
+                    }
+                }
+                _ => collect_references(object, &mut pending),
+            }
+        }
+    }
+
+    seen
+}
+
+/// Collect every `Object::Reference` nested in `object` into `refs`.
+fn collect_references(object: &Object, refs: &mut Vec<ObjectId>) {
+    match object {
+        Object::Reference(object_id) => refs.push(*object_id),
+        Object::Array(array) => {
+            for item in array {
+                collect_references(item, refs);
+            }
+        }
+        Object::Dictionary(dictionary) => {
+            for (_, item) in dictionary.iter() {
+                collect_references(item, refs);
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, item) in stream.dict.iter() {
+                collect_references(item, refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn save(document: &mut Document) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    document.save_to(&mut bytes)?;
+    Ok(bytes)
+}